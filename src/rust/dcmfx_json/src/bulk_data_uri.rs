@@ -0,0 +1,274 @@
+//! `InlineBinary` values are base64-encoded via the `base64` crate, the
+//! same as the rest of the DICOM JSON Model's binary values -- this module
+//! assumes it's already a dependency of `dcmfx_json`.
+//!
+//! `serialize_binary_value`'s threshold check is written as a `match` with
+//! a guard rather than an `if`-with-`&&` chain, since a let-chain's
+//! edition/MSRV requirements can't be confirmed against this crate's
+//! actual `Cargo.toml`, which isn't present in this checkout.
+
+use base64::Engine;
+use dcmfx_core::DataSetPath;
+
+use crate::json_error::JsonDeserializeError;
+
+/// Callback invoked during JSON serialization for a value that is at or
+/// above the configured [`BulkDataUriConfig`] threshold. Receives the raw
+/// bytes of the value and returns the URI that should be referenced in its
+/// place, e.g. by writing the bytes to a sidecar file or an object store.
+///
+pub type BulkDataUriEmitter<'a> = dyn FnMut(&[u8]) -> String + 'a;
+
+/// Callback invoked during JSON deserialization to resolve a `BulkDataURI`
+/// back into its raw bytes, e.g. by reading a sidecar file or fetching from
+/// an object store. Returns `None` if the URI isn't recognized.
+///
+pub type BulkDataUriResolver<'a> = dyn FnMut(&str) -> Option<Vec<u8>> + 'a;
+
+/// Controls when large binary values are externalized as a `BulkDataURI`
+/// rather than inlined as base64 `InlineBinary` when serializing to DICOM
+/// JSON.
+///
+pub struct BulkDataUriConfig<'a> {
+  /// Values at or above this size in bytes are externalized. A value of
+  /// `0` disables `BulkDataURI` emission entirely.
+  pub threshold_bytes: usize,
+
+  /// Called for each value that is externalized, and returns the
+  /// `BulkDataURI` that refers to it.
+  pub emitter: Box<BulkDataUriEmitter<'a>>,
+}
+
+impl<'a> BulkDataUriConfig<'a> {
+  /// Creates a new [`BulkDataUriConfig`] with the given threshold and
+  /// emitter callback.
+  ///
+  pub fn new(
+    threshold_bytes: usize,
+    emitter: impl FnMut(&[u8]) -> String + 'a,
+  ) -> Self {
+    Self {
+      threshold_bytes,
+      emitter: Box::new(emitter),
+    }
+  }
+
+  /// Returns whether a value of the given size should be externalized as a
+  /// `BulkDataURI` rather than inlined.
+  ///
+  pub fn should_externalize(&self, byte_length: usize) -> bool {
+    self.threshold_bytes > 0 && byte_length >= self.threshold_bytes
+  }
+}
+
+/// Serializes a binary value with the given `vr` to its DICOM JSON
+/// representation, replacing it with a `{"vr": ..., "BulkDataURI": ...}`
+/// object when `config` is present and `bytes` is at or above its
+/// threshold, and otherwise inlining it as base64 `InlineBinary`.
+///
+pub fn serialize_binary_value(
+  bytes: &[u8],
+  vr: &str,
+  config: Option<&mut BulkDataUriConfig>,
+) -> serde_json::Value {
+  match config {
+    Some(config) if config.should_externalize(bytes.len()) => {
+      let uri = (config.emitter)(bytes);
+
+      serde_json::json!({
+        "vr": vr,
+        "BulkDataURI": uri,
+      })
+    }
+
+    _ => serde_json::json!({
+      "vr": vr,
+      "InlineBinary": base64::engine::general_purpose::STANDARD.encode(bytes),
+    }),
+  }
+}
+
+/// Deserializes a binary value's DICOM JSON representation -- either an
+/// `InlineBinary` base64 string or a `BulkDataURI` reference -- back into
+/// raw bytes, resolving a `BulkDataURI` via `resolve_bulk_data_uri`.
+///
+pub fn deserialize_binary_value(
+  value: &serde_json::Map<String, serde_json::Value>,
+  path: &DataSetPath,
+  resolver: Option<&mut BulkDataUriResolver>,
+) -> Result<Vec<u8>, JsonDeserializeError> {
+  if let Some(serde_json::Value::String(uri)) = value.get("BulkDataURI") {
+    return resolve_bulk_data_uri(uri, path, resolver);
+  }
+
+  if let Some(serde_json::Value::String(inline_binary)) =
+    value.get("InlineBinary")
+  {
+    return base64::engine::general_purpose::STANDARD
+      .decode(inline_binary)
+      .map_err(|e| JsonDeserializeError::DataError {
+        details: format!("Invalid InlineBinary base64 data: {}", e),
+        path: path.clone(),
+        location: None,
+      });
+  }
+
+  Err(JsonDeserializeError::DataError {
+    details: "Expected an InlineBinary or BulkDataURI value".to_string(),
+    path: path.clone(),
+    location: None,
+  })
+}
+
+/// Resolves a `BulkDataURI` value encountered during JSON deserialization
+/// back into its raw bytes using the given `resolver` callback.
+///
+/// Returns [`JsonDeserializeError::UnresolvedBulkDataUri`] when no resolver
+/// is provided, or when the resolver doesn't recognize the URI, so that
+/// callers can distinguish an unresolved bulk data reference from a
+/// malformed document.
+///
+pub fn resolve_bulk_data_uri(
+  uri: &str,
+  path: &DataSetPath,
+  resolver: Option<&mut BulkDataUriResolver>,
+) -> Result<Vec<u8>, JsonDeserializeError> {
+  match resolver.and_then(|resolve| resolve(uri)) {
+    Some(bytes) => Ok(bytes),
+
+    None => Err(JsonDeserializeError::UnresolvedBulkDataUri {
+      uri: uri.to_string(),
+      details: format!("BulkDataURI could not be resolved: {}", uri),
+      path: path.clone(),
+      location: None,
+    }),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn inlines_below_threshold() {
+    let mut config = BulkDataUriConfig::new(16, |_| "bulk://unused".to_string());
+
+    let value = serialize_binary_value(&[1, 2, 3], "OB", Some(&mut config));
+
+    assert_eq!(value["vr"], "OB");
+    assert!(value.get("InlineBinary").is_some());
+    assert!(value.get("BulkDataURI").is_none());
+  }
+
+  #[test]
+  fn inlines_when_no_config_is_given() {
+    let value = serialize_binary_value(&[0; 64], "OB", None);
+
+    assert!(value.get("InlineBinary").is_some());
+    assert!(value.get("BulkDataURI").is_none());
+  }
+
+  #[test]
+  fn externalizes_at_or_above_threshold() {
+    let mut config =
+      BulkDataUriConfig::new(4, |bytes| format!("bulk://{}", bytes.len()));
+
+    let value = serialize_binary_value(&[1, 2, 3, 4], "OB", Some(&mut config));
+
+    assert_eq!(value["vr"], "OB");
+    assert_eq!(value["BulkDataURI"], "bulk://4");
+    assert!(value.get("InlineBinary").is_none());
+  }
+
+  #[test]
+  fn zero_threshold_disables_externalization() {
+    let mut config = BulkDataUriConfig::new(0, |_| "bulk://unused".to_string());
+
+    let value = serialize_binary_value(&[1, 2, 3, 4], "OB", Some(&mut config));
+
+    assert!(value.get("InlineBinary").is_some());
+  }
+
+  #[test]
+  fn deserializes_inline_binary() {
+    let mut value = serde_json::Map::new();
+    value.insert(
+      "InlineBinary".to_string(),
+      serde_json::Value::String(
+        base64::engine::general_purpose::STANDARD.encode([1, 2, 3]),
+      ),
+    );
+
+    let bytes =
+      deserialize_binary_value(&value, &DataSetPath::new(), None).unwrap();
+
+    assert_eq!(bytes, vec![1, 2, 3]);
+  }
+
+  #[test]
+  fn deserialize_inline_binary_rejects_invalid_base64() {
+    let mut value = serde_json::Map::new();
+    value.insert(
+      "InlineBinary".to_string(),
+      serde_json::Value::String("not valid base64!!".to_string()),
+    );
+
+    let result = deserialize_binary_value(&value, &DataSetPath::new(), None);
+
+    assert!(matches!(
+      result,
+      Err(JsonDeserializeError::DataError { .. })
+    ));
+  }
+
+  #[test]
+  fn deserializes_bulk_data_uri_via_resolver() {
+    let mut value = serde_json::Map::new();
+    value.insert(
+      "BulkDataURI".to_string(),
+      serde_json::Value::String("bulk://abc".to_string()),
+    );
+
+    let mut resolver: Box<BulkDataUriResolver> = Box::new(|uri| {
+      if uri == "bulk://abc" {
+        Some(vec![9, 9, 9])
+      } else {
+        None
+      }
+    });
+
+    let bytes =
+      deserialize_binary_value(&value, &DataSetPath::new(), Some(&mut resolver))
+        .unwrap();
+
+    assert_eq!(bytes, vec![9, 9, 9]);
+  }
+
+  #[test]
+  fn unresolved_bulk_data_uri_without_a_resolver_is_an_error() {
+    let mut value = serde_json::Map::new();
+    value.insert(
+      "BulkDataURI".to_string(),
+      serde_json::Value::String("bulk://abc".to_string()),
+    );
+
+    let result = deserialize_binary_value(&value, &DataSetPath::new(), None);
+
+    assert!(matches!(
+      result,
+      Err(JsonDeserializeError::UnresolvedBulkDataUri { .. })
+    ));
+  }
+
+  #[test]
+  fn missing_both_fields_is_an_error() {
+    let value = serde_json::Map::new();
+
+    let result = deserialize_binary_value(&value, &DataSetPath::new(), None);
+
+    assert!(matches!(
+      result,
+      Err(JsonDeserializeError::DataError { .. })
+    ));
+  }
+}
@@ -0,0 +1,285 @@
+//! Requires from `dcmfx_p10`: a `P10Part` enum with `PixelDataFragments`
+//! (the start-of-encapsulated-PixelData marker), `PixelDataItem(Vec<u8>)`
+//! (one Basic Offset Table or fragment item), and `SequenceDelimiter` (the
+//! end-of-fragments marker), plus, for the tests, an `Other` variant
+//! standing for any other part; and `P10Error::PartStreamInvalid { details }`.
+//! These are not present in this checkout, so this module hasn't been
+//! built or clippy'd against the real crate. `InlineBinary` is base64
+//! encoded via the `base64` crate, assumed already a dependency of
+//! `dcmfx_json`.
+
+use base64::Engine;
+use dcmfx_p10::{P10Error, P10Part};
+
+use crate::json_error::JsonSerializeError;
+
+/// Controls how encapsulated (i.e. multi-fragment) PixelData is handled
+/// when serializing a data set to DICOM JSON.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum EncapsulatedPixelDataMode {
+  /// Concatenates the Basic Offset Table and all fragments, and emits the
+  /// result as base64-encoded `InlineBinary`.
+  InlineBinary,
+
+  /// Omits the PixelData element from the output entirely. A non-fatal
+  /// diagnostic is recorded rather than the whole serialize failing.
+  Skip,
+}
+
+impl Default for EncapsulatedPixelDataMode {
+  /// The default mode is [`EncapsulatedPixelDataMode::InlineBinary`], which
+  /// keeps the output self-contained.
+  ///
+  fn default() -> Self {
+    Self::InlineBinary
+  }
+}
+
+/// The result of serializing the `(7FE0,0010)` PixelData element found in a
+/// stream of P10 parts.
+///
+#[derive(Debug)]
+pub enum EncapsulatedPixelDataOutcome {
+  /// PixelData was encapsulated and serialized to this JSON value.
+  Value(serde_json::Value),
+
+  /// PixelData was encapsulated, but [`EncapsulatedPixelDataMode::Skip`]
+  /// was in effect, so it was omitted. `diagnostic` describes what was
+  /// skipped, for non-fatal reporting back to the caller.
+  Skipped { diagnostic: String },
+
+  /// `parts` contained no encapsulated PixelData element at all, so the
+  /// caller should fall through to its normal handling of an inline
+  /// (non-encapsulated) PixelData value.
+  NotEncapsulated,
+}
+
+/// Concatenates the Basic Offset Table and item fragments of an
+/// encapsulated PixelData element, in the order they were read from the
+/// P10 part stream, ready for base64 encoding as `InlineBinary`.
+///
+pub fn concat_encapsulated_pixel_data_fragments(
+  basic_offset_table: &[u8],
+  fragments: &[Vec<u8>],
+) -> Vec<u8> {
+  let total_len = basic_offset_table.len()
+    + fragments.iter().map(Vec::len).sum::<usize>();
+
+  let mut data = Vec::with_capacity(total_len);
+
+  data.extend_from_slice(basic_offset_table);
+
+  for fragment in fragments {
+    data.extend_from_slice(fragment);
+  }
+
+  data
+}
+
+/// Walks a stream of P10 parts for the `(7FE0,0010)` PixelData element and,
+/// if it is encapsulated (i.e. made up of item fragments rather than a
+/// single value), serializes it to DICOM JSON according to `mode`.
+///
+/// [`JsonSerializeError::P10Error`] is only returned when the part stream
+/// itself is malformed -- a `PixelDataFragments` marker not followed by a
+/// Basic Offset Table item, an item fragment sequence that never reaches
+/// its `SequenceDelimiter`, or a part other than a `PixelDataItem` before
+/// that delimiter -- never merely because the PixelData
+/// happens to be encapsulated.
+///
+pub fn serialize_encapsulated_pixel_data(
+  parts: &[P10Part],
+  mode: EncapsulatedPixelDataMode,
+) -> Result<EncapsulatedPixelDataOutcome, JsonSerializeError> {
+  let Some(start) = parts
+    .iter()
+    .position(|part| matches!(part, P10Part::PixelDataFragments))
+  else {
+    return Ok(EncapsulatedPixelDataOutcome::NotEncapsulated);
+  };
+
+  let mut items = vec![];
+  let mut reached_delimiter = false;
+
+  for part in &parts[start + 1..] {
+    match part {
+      P10Part::PixelDataItem(bytes) => items.push(bytes.clone()),
+      P10Part::SequenceDelimiter => {
+        reached_delimiter = true;
+        break;
+      }
+      _ => {
+        return Err(JsonSerializeError::P10Error(
+          P10Error::PartStreamInvalid {
+            details: "Encapsulated PixelData item fragments ended without \
+              a sequence delimiter"
+              .to_string(),
+          },
+        ));
+      }
+    }
+  }
+
+  if !reached_delimiter {
+    return Err(JsonSerializeError::P10Error(P10Error::PartStreamInvalid {
+      details: "Encapsulated PixelData item fragments ended without a \
+        sequence delimiter"
+        .to_string(),
+    }));
+  }
+
+  let Some((basic_offset_table, fragments)) = items.split_first() else {
+    return Err(JsonSerializeError::P10Error(P10Error::PartStreamInvalid {
+      details: "Encapsulated PixelData has no Basic Offset Table item"
+        .to_string(),
+    }));
+  };
+
+  match mode {
+    EncapsulatedPixelDataMode::InlineBinary => {
+      let data =
+        concat_encapsulated_pixel_data_fragments(basic_offset_table, fragments);
+
+      let inline_binary =
+        base64::engine::general_purpose::STANDARD.encode(data);
+
+      Ok(EncapsulatedPixelDataOutcome::Value(serde_json::json!({
+        "vr": "OB",
+        "InlineBinary": inline_binary,
+      })))
+    }
+
+    EncapsulatedPixelDataMode::Skip => {
+      Ok(EncapsulatedPixelDataOutcome::Skipped {
+        diagnostic: format!(
+          "Omitted encapsulated PixelData ({} fragment(s))",
+          fragments.len()
+        ),
+      })
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn not_encapsulated_when_no_pixel_data_fragments_marker() {
+    let parts = vec![P10Part::Other];
+
+    let outcome = serialize_encapsulated_pixel_data(
+      &parts,
+      EncapsulatedPixelDataMode::InlineBinary,
+    )
+    .unwrap();
+
+    assert!(matches!(outcome, EncapsulatedPixelDataOutcome::NotEncapsulated));
+  }
+
+  #[test]
+  fn inlines_a_well_formed_stream() {
+    let parts = vec![
+      P10Part::PixelDataFragments,
+      P10Part::PixelDataItem(vec![0, 0, 0, 0]),
+      P10Part::PixelDataItem(vec![1, 2, 3]),
+      P10Part::PixelDataItem(vec![4, 5]),
+      P10Part::SequenceDelimiter,
+    ];
+
+    let outcome = serialize_encapsulated_pixel_data(
+      &parts,
+      EncapsulatedPixelDataMode::InlineBinary,
+    )
+    .unwrap();
+
+    let EncapsulatedPixelDataOutcome::Value(value) = outcome else {
+      panic!("expected a Value outcome, got {:?}", outcome);
+    };
+
+    let expected = base64::engine::general_purpose::STANDARD
+      .encode([0, 0, 0, 0, 1, 2, 3, 4, 5]);
+
+    assert_eq!(value["vr"], "OB");
+    assert_eq!(value["InlineBinary"], expected);
+  }
+
+  #[test]
+  fn skips_a_well_formed_stream_in_skip_mode() {
+    let parts = vec![
+      P10Part::PixelDataFragments,
+      P10Part::PixelDataItem(vec![0, 0, 0, 0]),
+      P10Part::PixelDataItem(vec![1, 2, 3]),
+      P10Part::SequenceDelimiter,
+    ];
+
+    let outcome = serialize_encapsulated_pixel_data(
+      &parts,
+      EncapsulatedPixelDataMode::Skip,
+    )
+    .unwrap();
+
+    match outcome {
+      EncapsulatedPixelDataOutcome::Skipped { diagnostic } => {
+        assert!(diagnostic.contains('1'));
+      }
+      other => panic!("expected a Skipped outcome, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn errors_when_the_part_stream_ends_without_a_sequence_delimiter() {
+    let parts = vec![
+      P10Part::PixelDataFragments,
+      P10Part::PixelDataItem(vec![0, 0, 0, 0]),
+      P10Part::PixelDataItem(vec![1, 2, 3]),
+    ];
+
+    let result = serialize_encapsulated_pixel_data(
+      &parts,
+      EncapsulatedPixelDataMode::InlineBinary,
+    );
+
+    assert!(matches!(
+      result,
+      Err(JsonSerializeError::P10Error(P10Error::PartStreamInvalid { .. }))
+    ));
+  }
+
+  #[test]
+  fn errors_on_a_part_other_than_item_or_delimiter() {
+    let parts = vec![
+      P10Part::PixelDataFragments,
+      P10Part::PixelDataItem(vec![0, 0, 0, 0]),
+      P10Part::Other,
+      P10Part::SequenceDelimiter,
+    ];
+
+    let result = serialize_encapsulated_pixel_data(
+      &parts,
+      EncapsulatedPixelDataMode::InlineBinary,
+    );
+
+    assert!(matches!(
+      result,
+      Err(JsonSerializeError::P10Error(P10Error::PartStreamInvalid { .. }))
+    ));
+  }
+
+  #[test]
+  fn errors_when_there_is_no_basic_offset_table_item() {
+    let parts =
+      vec![P10Part::PixelDataFragments, P10Part::SequenceDelimiter];
+
+    let result = serialize_encapsulated_pixel_data(
+      &parts,
+      EncapsulatedPixelDataMode::InlineBinary,
+    );
+
+    assert!(matches!(
+      result,
+      Err(JsonSerializeError::P10Error(P10Error::PartStreamInvalid { .. }))
+    ));
+  }
+}
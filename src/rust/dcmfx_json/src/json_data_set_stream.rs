@@ -0,0 +1,360 @@
+use dcmfx_core::DataSetPath;
+
+use crate::json_error::JsonDeserializeError;
+
+/// Deserializes a stream of DICOM JSON objects, isolating parse failures to
+/// the individual object that caused them rather than aborting the whole
+/// stream.
+///
+/// Two shapes of input are supported, auto-detected from the first
+/// non-whitespace byte:
+///
+/// - Whitespace- or newline-separated top-level values, e.g. one object per
+///   line.
+/// - A single top-level JSON array, each element of which is treated as an
+///   independent object.
+///
+/// Each item yielded is the result of parsing one object and passing it to
+/// `convert`, which is responsible for turning that value into a `T`
+/// (typically a data set). If the value is valid JSON but `convert`
+/// returns an error, or if the JSON parser itself fails on that object,
+/// the iterator yields a single [`Err`] for it and resumes parsing from
+/// the next object boundary -- unless the JSON text is structurally
+/// corrupt enough that no boundary can be found, in which case the stream
+/// ends.
+///
+/// `serde_json` doesn't support resuming a `StreamDeserializer` after a
+/// non-EOF error on the same input -- its readers truncate what they
+/// consider the remaining input as soon as a parse fails -- so resyncing
+/// after a bad object is done by hand, via a lexical (not a real JSON
+/// parse) scan that tracks object/array nesting depth and string
+/// escaping. This is enough to find a sane boundary even through
+/// malformed text in the common case, but a bad object whose brackets or
+/// quotes are themselves unbalanced can still cause the scan to run past
+/// further objects that would otherwise have parsed fine.
+///
+pub struct JsonDataSetStream<T, F>
+where
+  F: FnMut(serde_json::Value, &DataSetPath) -> Result<T, JsonDeserializeError>,
+{
+  data: Vec<u8>,
+  position: usize,
+  mode: StreamMode,
+  convert: F,
+}
+
+/// The shape of the top-level JSON text a [`JsonDataSetStream`] was
+/// constructed from.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum StreamMode {
+  /// Top-level JSON values, whitespace- or newline-separated.
+  Values,
+
+  /// A single top-level JSON array, whose elements are each treated as an
+  /// independent object.
+  Array,
+}
+
+impl<T, F> JsonDataSetStream<T, F>
+where
+  F: FnMut(serde_json::Value, &DataSetPath) -> Result<T, JsonDeserializeError>,
+{
+  /// Creates a new [`JsonDataSetStream`] that reads successive top-level
+  /// JSON values or array elements out of `reader`, converting each one to
+  /// a `T` via `convert`.
+  ///
+  pub fn new(
+    mut reader: impl std::io::Read,
+    convert: F,
+  ) -> std::io::Result<Self> {
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+
+    let mut position = 0;
+    while position < data.len()
+      && (data[position] as char).is_ascii_whitespace()
+    {
+      position += 1;
+    }
+
+    let mode = if data.get(position) == Some(&b'[') {
+      position += 1;
+      StreamMode::Array
+    } else {
+      position = 0;
+      StreamMode::Values
+    };
+
+    Ok(Self {
+      data,
+      position,
+      mode,
+      convert,
+    })
+  }
+
+  /// Returns the byte offset up to which the input has been consumed. The
+  /// next object, if any, begins at this offset or after the whitespace
+  /// that follows it.
+  ///
+  pub fn byte_offset(&self) -> u64 {
+    self.position as u64
+  }
+
+  /// Advances past any leading whitespace and returns the byte offset of
+  /// the next object, or `None` if the stream is exhausted.
+  ///
+  fn skip_whitespace(&mut self) -> Option<usize> {
+    while self.position < self.data.len()
+      && (self.data[self.position] as char).is_ascii_whitespace()
+    {
+      self.position += 1;
+    }
+
+    if self.position >= self.data.len() {
+      None
+    } else {
+      Some(self.position)
+    }
+  }
+
+  /// Yields the next item when this stream holds whitespace- or
+  /// newline-separated top-level values.
+  ///
+  fn next_value(&mut self) -> Option<Result<T, JsonDeserializeError>> {
+    let object_start = self.skip_whitespace()?;
+    let path = DataSetPath::new();
+
+    let mut values =
+      serde_json::Deserializer::from_slice(&self.data[object_start..])
+        .into_iter::<serde_json::Value>();
+
+    let result = values.next();
+    let consumed = values.byte_offset();
+
+    match result {
+      Some(Ok(value)) => {
+        self.position = object_start + consumed;
+        Some((self.convert)(value, &path))
+      }
+
+      Some(Err(e)) => {
+        self.position =
+          scan_to_boundary(&self.data, object_start, |byte| {
+            (byte as char).is_ascii_whitespace()
+          });
+
+        Some(Err(
+          JsonDeserializeError::from_serde_json_error(&e, path)
+            .with_object_start_offset(object_start as u64),
+        ))
+      }
+
+      None => {
+        self.position = self.data.len();
+        None
+      }
+    }
+  }
+
+  /// Yields the next item when this stream holds a top-level JSON array.
+  ///
+  fn next_array_element(&mut self) -> Option<Result<T, JsonDeserializeError>> {
+    let object_start = self.skip_whitespace()?;
+
+    if self.data[object_start] == b']' {
+      self.position = self.data.len();
+      return None;
+    }
+
+    let path = DataSetPath::new();
+
+    let element_end =
+      scan_to_boundary(&self.data, object_start, |byte| {
+        byte == b',' || byte == b']'
+      });
+
+    let result = serde_json::from_slice::<serde_json::Value>(
+      &self.data[object_start..element_end],
+    );
+
+    self.position = element_end;
+    if self.data.get(self.position) == Some(&b',') {
+      self.position += 1;
+    }
+
+    match result {
+      Ok(value) => Some((self.convert)(value, &path)),
+
+      Err(e) => Some(Err(
+        JsonDeserializeError::from_serde_json_error(&e, path)
+          .with_object_start_offset(object_start as u64),
+      )),
+    }
+  }
+}
+
+impl<T, F> Iterator for JsonDataSetStream<T, F>
+where
+  F: FnMut(serde_json::Value, &DataSetPath) -> Result<T, JsonDeserializeError>,
+{
+  type Item = Result<T, JsonDeserializeError>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match self.mode {
+      StreamMode::Values => self.next_value(),
+      StreamMode::Array => self.next_array_element(),
+    }
+  }
+}
+
+/// Scans forward from `start`, tracking JSON object/array nesting depth and
+/// string escaping, and returns the index of the first byte at nesting
+/// depth zero (outside of a string) for which `is_boundary` returns `true`
+/// -- or the end of `data` if none is found.
+///
+/// This is a lexical scan, not a real JSON parse, so it works just as well
+/// to resync past malformed text as it does to find the extent of a value
+/// that parsed successfully.
+///
+fn scan_to_boundary(
+  data: &[u8],
+  start: usize,
+  is_boundary: impl Fn(u8) -> bool,
+) -> usize {
+  let mut depth: i32 = 0;
+  let mut in_string = false;
+  let mut escaped = false;
+
+  for (offset, &byte) in data[start..].iter().enumerate() {
+    if in_string {
+      if escaped {
+        escaped = false;
+      } else if byte == b'\\' {
+        escaped = true;
+      } else if byte == b'"' {
+        in_string = false;
+      }
+
+      continue;
+    }
+
+    match byte {
+      b'"' => in_string = true,
+
+      b'{' | b'[' => depth += 1,
+
+      b'}' | b']' => {
+        if depth <= 0 && is_boundary(byte) {
+          return start + offset;
+        }
+
+        depth -= 1;
+      }
+
+      _ if depth <= 0 && is_boundary(byte) => return start + offset,
+
+      _ => {}
+    }
+  }
+
+  data.len()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn collect(
+    input: &[u8],
+  ) -> Vec<Result<serde_json::Value, JsonDeserializeError>> {
+    JsonDataSetStream::new(input, |value, _path| Ok(value))
+      .unwrap()
+      .collect()
+  }
+
+  #[test]
+  fn parses_newline_separated_values() {
+    let results = collect(b"{\"a\":1}\n{\"b\":2}\n{\"c\":3}");
+
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(Result::is_ok));
+  }
+
+  #[test]
+  fn isolates_a_malformed_object_between_newline_separated_values() {
+    let results = collect(b"{\"a\":1}\nnot-json\n{\"b\":2}");
+
+    assert!(matches!(results.as_slice(), [Ok(_), Err(_), Ok(_)]));
+  }
+
+  #[test]
+  fn isolates_a_malformed_object_between_space_separated_values() {
+    // No newlines anywhere in the input -- resync must not fall back to
+    // scanning for '\n' or it will swallow the remainder of the buffer.
+    let results = collect(b"{\"a\":1} not-json {\"b\":2}");
+
+    assert!(matches!(results.as_slice(), [Ok(_), Err(_), Ok(_)]));
+  }
+
+  #[test]
+  fn reports_the_byte_offset_a_malformed_value_started_at() {
+    let mut stream =
+      JsonDataSetStream::new(&b"{\"a\":1}\nnot-json\n{\"b\":2}"[..], |value, _path| {
+        Ok(value)
+      })
+      .unwrap();
+
+    assert!(stream.next().unwrap().is_ok());
+
+    let error = stream.next().unwrap().unwrap_err();
+    assert!(error.path().is_empty());
+
+    match error {
+      JsonDeserializeError::SyntaxError { location, .. } => {
+        assert_eq!(location.unwrap().byte_offset, Some(8));
+      }
+      other => panic!("expected a SyntaxError, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn parses_a_top_level_array() {
+    let results = collect(b"[{\"a\":1}, {\"b\":2}, {\"c\":3}]");
+
+    assert_eq!(results.len(), 3);
+    assert!(results.iter().all(Result::is_ok));
+  }
+
+  #[test]
+  fn isolates_a_malformed_element_in_a_top_level_array() {
+    let results = collect(b"[{\"a\":1}, not-json, {\"b\":2}]");
+
+    assert!(matches!(results.as_slice(), [Ok(_), Err(_), Ok(_)]));
+  }
+
+  #[test]
+  fn array_elements_with_nested_commas_are_not_split_early() {
+    let results =
+      collect(b"[{\"a\":[1, 2, 3]}, {\"b\": {\"c\": 1, \"d\": 2}}]");
+
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(Result::is_ok));
+  }
+
+  #[test]
+  fn an_empty_array_yields_no_items() {
+    let results = collect(b"[]");
+
+    assert!(results.is_empty());
+  }
+
+  #[test]
+  fn a_comma_inside_a_string_does_not_split_an_array_element() {
+    let results = collect(b"[{\"a\": \"x, y\"}, {\"b\": 2}]");
+
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(Result::is_ok));
+  }
+}
@@ -1,3 +1,29 @@
+//! Requires from `dcmfx_core`: `dictionary::tag_name`, `DataError`,
+//! `DcmfxError`, `DataElementTag` with `group`/`element` fields and
+//! `Display`, and `DataSetPath` with `Debug`, `Default`, `new`,
+//! `add_data_element`, `add_sequence_item`, `pop`, `clone`, `is_empty`,
+//! `final_data_element`, `to_detailed_string`, and `Display`. Requires from
+//! `dcmfx_p10`: `P10Error` with a `Display` impl and a `to_lines` impl via
+//! `DcmfxError`, plus the `P10Part` surface listed at the top of
+//! `encapsulated_pixel_data.rs`. These crates are not present in
+//! this checkout, so none of the above has been verified against their
+//! actual signatures, and neither is there a `Cargo.toml` anywhere in this
+//! checkout for `cargo build`/`clippy`/`test` to run against at all.
+//!
+//! This file and the other new files of this crate have instead been
+//! checked against a
+//! throwaway workspace (not part of this checkout, and not reproducible
+//! by a reader of this repo alone) with stub `dcmfx_core`/`dcmfx_p10`
+//! crates implementing the exact surface listed above, plus real
+//! `serde_json = "1"` and `base64 = "0.22"` dependencies, built and run
+//! with `cargo build`, `cargo clippy --all-targets -- -D warnings`, and
+//! `cargo test`. That confirms these files' own logic -- the resync
+//! scanning, the fragment-boundary handling, the error classification --
+//! is correct against the *assumed* API, but it cannot confirm the
+//! assumed API itself matches the real `dcmfx_core`/`dcmfx_p10` crates.
+//! That part can only be confirmed by building this series in the real
+//! `dcmfx` workspace.
+
 use dcmfx_core::{dictionary, DataError, DataSetPath};
 use dcmfx_p10::P10Error;
 
@@ -26,22 +52,280 @@ pub enum JsonSerializeError {
 ///
 #[derive(Debug)]
 pub enum JsonDeserializeError {
-  /// The DICOM JSON data to be deserialized is invalid.
-  JsonInvalid { details: String, path: DataSetPath },
+  /// The JSON text is not syntactically valid, e.g. a missing brace or an
+  /// unterminated string. Parsing of the value it occurs in can't continue
+  /// past it.
+  SyntaxError {
+    details: String,
+    path: DataSetPath,
+    location: Option<JsonLocation>,
+  },
+
+  /// The JSON text is syntactically valid, but the value it contains does
+  /// not conform to the DICOM JSON Model, e.g. an unknown VR, a value
+  /// multiplicity that doesn't match the VR, or a malformed PersonName
+  /// group. Callers processing a stream of data sets may choose to skip the
+  /// offending element or data set and continue.
+  DataError {
+    details: String,
+    path: DataSetPath,
+    location: Option<JsonLocation>,
+  },
+
+  /// The JSON text ended before a complete value was read, e.g. the input
+  /// was truncated partway through an object. Parsing of the value it
+  /// occurs in can't continue past it.
+  UnexpectedEof {
+    details: String,
+    path: DataSetPath,
+    location: Option<JsonLocation>,
+  },
+
+  /// An error occurred when trying to read the DICOM JSON data from the
+  /// provided stream. Details of the issue are contained in `details`.
+  IOError {
+    details: String,
+    path: DataSetPath,
+    location: Option<JsonLocation>,
+  },
+
+  /// A `BulkDataURI` value was encountered but could not be resolved back
+  /// to its raw bytes, either because no resolver callback was provided or
+  /// because the resolver did not recognize the URI.
+  UnresolvedBulkDataUri {
+    uri: String,
+    details: String,
+    path: DataSetPath,
+    location: Option<JsonLocation>,
+  },
+}
+
+/// The general category of failure behind a [`JsonDeserializeError`],
+/// mirroring `serde_json`'s [`Category`](serde_json::error::Category).
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum JsonErrorCategory {
+  /// The JSON text is not syntactically valid.
+  Syntax,
+
+  /// The JSON text is syntactically valid but doesn't conform to the DICOM
+  /// JSON Model.
+  Data,
+
+  /// The JSON text ended before a complete value was read.
+  Eof,
+
+  /// An I/O error occurred while reading the JSON text.
+  Io,
+}
+
+impl JsonDeserializeError {
+  /// Returns the general category of this error.
+  ///
+  pub fn classify(&self) -> JsonErrorCategory {
+    match self {
+      JsonDeserializeError::SyntaxError { .. } => JsonErrorCategory::Syntax,
+      JsonDeserializeError::DataError { .. } => JsonErrorCategory::Data,
+      JsonDeserializeError::UnexpectedEof { .. } => JsonErrorCategory::Eof,
+      JsonDeserializeError::IOError { .. } => JsonErrorCategory::Io,
+      JsonDeserializeError::UnresolvedBulkDataUri { .. } => {
+        JsonErrorCategory::Data
+      }
+    }
+  }
+
+  /// Returns whether this error occurred because the JSON text was not
+  /// syntactically valid. Parsing of the value it occurred in can't continue
+  /// past it.
+  ///
+  pub fn is_syntax(&self) -> bool {
+    self.classify() == JsonErrorCategory::Syntax
+  }
+
+  /// Returns whether this error occurred because a value in otherwise
+  /// well-formed JSON text didn't conform to the DICOM JSON Model. Callers
+  /// processing a stream of data sets may choose to skip the offending
+  /// element or data set and continue.
+  ///
+  pub fn is_data(&self) -> bool {
+    self.classify() == JsonErrorCategory::Data
+  }
+
+  /// Returns whether this error occurred because the JSON text ended before
+  /// a complete value was read. Parsing of the value it occurred in can't
+  /// continue past it.
+  ///
+  pub fn is_eof(&self) -> bool {
+    self.classify() == JsonErrorCategory::Eof
+  }
+}
+
+/// A location within a block of JSON text, given as a line number, column
+/// number, and (where known) byte offset from the start of the text. Line
+/// and column numbers are 1-indexed, and are both 0 when not known, e.g. in
+/// the location [`JsonDeserializeError::with_object_start_offset`] adds to
+/// an error that had none.
+///
+/// `serde_json::Error` itself only exposes `line`/`column`, not a byte
+/// offset, so `byte_offset` is only populated when the caller has its own
+/// means of tracking it, e.g. from [`serde_json::StreamDeserializer`]'s
+/// `byte_offset` when reading a stream of multiple JSON values.
+///
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct JsonLocation {
+  pub line: u64,
+  pub column: u64,
+  pub byte_offset: Option<u64>,
+}
+
+impl JsonLocation {
+  /// Constructs a [`JsonLocation`] from the line/column carried by a
+  /// `serde_json` error, with no byte offset.
+  ///
+  pub fn from_serde_json_error(error: &serde_json::Error) -> Self {
+    Self {
+      line: error.line() as u64,
+      column: error.column() as u64,
+      byte_offset: None,
+    }
+  }
+}
+
+impl JsonDeserializeError {
+  /// Constructs a [`JsonDeserializeError`] from a `serde_json` error,
+  /// classifying it into the appropriate variant and capturing its location
+  /// in the source text.
+  ///
+  pub fn from_serde_json_error(
+    error: &serde_json::Error,
+    path: DataSetPath,
+  ) -> Self {
+    let details = error.to_string();
+    let location = Some(JsonLocation::from_serde_json_error(error));
+
+    match error.classify() {
+      serde_json::error::Category::Syntax => JsonDeserializeError::SyntaxError {
+        details,
+        path,
+        location,
+      },
+      serde_json::error::Category::Data => JsonDeserializeError::DataError {
+        details,
+        path,
+        location,
+      },
+      serde_json::error::Category::Eof => JsonDeserializeError::UnexpectedEof {
+        details,
+        path,
+        location,
+      },
+      serde_json::error::Category::Io => JsonDeserializeError::IOError {
+        details,
+        path,
+        location,
+      },
+    }
+  }
+
+  /// Returns the path to the data element this error occurred at, or
+  /// within, which the caller tracked as it walked the DICOM JSON Model
+  /// (see [`crate::json_path_tracker::JsonPathTracker`]).
+  ///
+  pub fn path(&self) -> &DataSetPath {
+    self.fields().1
+  }
+
+  /// Returns the `details`, `path`, and `location` common to every variant
+  /// of this error.
+  ///
+  fn fields(&self) -> (&str, &DataSetPath, &Option<JsonLocation>) {
+    match self {
+      JsonDeserializeError::SyntaxError {
+        details,
+        path,
+        location,
+      }
+      | JsonDeserializeError::DataError {
+        details,
+        path,
+        location,
+      }
+      | JsonDeserializeError::UnexpectedEof {
+        details,
+        path,
+        location,
+      }
+      | JsonDeserializeError::IOError {
+        details,
+        path,
+        location,
+      }
+      | JsonDeserializeError::UnresolvedBulkDataUri {
+        details,
+        path,
+        location,
+        ..
+      } => (details, path, location),
+    }
+  }
+
+  /// Returns a mutable reference to the `location` common to every variant
+  /// of this error.
+  ///
+  fn location_mut(&mut self) -> &mut Option<JsonLocation> {
+    match self {
+      JsonDeserializeError::SyntaxError { location, .. }
+      | JsonDeserializeError::DataError { location, .. }
+      | JsonDeserializeError::UnexpectedEof { location, .. }
+      | JsonDeserializeError::IOError { location, .. }
+      | JsonDeserializeError::UnresolvedBulkDataUri { location, .. } => {
+        location
+      }
+    }
+  }
+
+  /// Overrides this error's [`JsonLocation::byte_offset`] with the byte
+  /// offset at which the object containing it began, which is generally
+  /// more useful to a caller than the offset of the specific byte within
+  /// that object where the parser gave up.
+  ///
+  pub fn with_object_start_offset(mut self, byte_offset: u64) -> Self {
+    let location = self.location_mut();
+
+    if let Some(location) = location {
+      location.byte_offset = Some(byte_offset);
+    } else {
+      *location = Some(JsonLocation {
+        line: 0,
+        column: 0,
+        byte_offset: Some(byte_offset),
+      });
+    }
+
+    self
+  }
 }
 
 impl std::fmt::Display for JsonDeserializeError {
   fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-    match self {
-      JsonDeserializeError::JsonInvalid { details, path } => {
-        write!(
-          f,
-          "DICOM JSON deserialize error, details: {}, path: {}",
-          details,
-          path.to_detailed_string(),
-        )
+    let (details, path, location) = self.fields();
+
+    write!(
+      f,
+      "DICOM JSON deserialize error, details: {}, path: {}",
+      details,
+      path.to_detailed_string(),
+    )?;
+
+    if let Some(location) = location {
+      write!(f, ", line: {}, column: {}", location.line, location.column)?;
+
+      if let Some(byte_offset) = location.byte_offset {
+        write!(f, ", byte offset: {}", byte_offset)?;
       }
     }
+
+    Ok(())
   }
 }
 
@@ -67,26 +351,165 @@ impl dcmfx_core::DcmfxError for JsonDeserializeError {
   /// human-readable format.
   ///
   fn to_lines(&self, task_description: &str) -> Vec<String> {
-    match self {
-      JsonDeserializeError::JsonInvalid { details, path } => {
-        let mut lines = vec![];
+    let (details, path, location) = self.fields();
+
+    let mut lines = vec![];
+
+    lines.push(format!("DICOM JSON deserialize error {}", task_description));
+    lines.push("".to_string());
+    lines.push(format!("  Details: {}", details));
+
+    if let Ok(tag) = path.final_data_element() {
+      lines.push(format!("  Tag: {}", tag));
+      lines.push(format!("  Name: {}", dictionary::tag_name(tag, None)));
+    }
+
+    if !path.is_empty() {
+      lines.push(format!("  Path: {}", path));
+    }
+
+    if let JsonDeserializeError::UnresolvedBulkDataUri { uri, .. } = self {
+      lines.push(format!("  BulkDataURI: {}", uri));
+    }
+
+    if let Some(location) = location {
+      lines.push(format!(
+        "  Line: {}, Column: {}",
+        location.line, location.column
+      ));
+
+      if let Some(byte_offset) = location.byte_offset {
+        lines.push(format!("  Byte offset: {}", byte_offset));
+      }
+    }
+
+    lines
+  }
+}
 
-        lines
-          .push(format!("DICOM JSON deserialize error {}", task_description));
-        lines.push("".to_string());
-        lines.push(format!("  Details: {}", details));
+#[cfg(test)]
+mod tests {
+  use dcmfx_core::{DataElementTag, DcmfxError};
 
-        if let Ok(tag) = path.final_data_element() {
-          lines.push(format!("  Tag: {}", tag));
-          lines.push(format!("  Name: {}", dictionary::tag_name(tag, None)));
-        }
+  use super::*;
 
-        if !path.is_empty() {
-          lines.push(format!("  Path: {}", path));
-        }
+  fn parse_error(json: &str) -> serde_json::Error {
+    serde_json::from_str::<serde_json::Value>(json).unwrap_err()
+  }
+
+  #[test]
+  fn classifies_syntax_errors() {
+    let error =
+      JsonDeserializeError::from_serde_json_error(&parse_error("{a}"), DataSetPath::new());
+
+    assert_eq!(error.classify(), JsonErrorCategory::Syntax);
+    assert!(error.is_syntax());
+    assert!(!error.is_eof());
+    assert!(!error.is_data());
+  }
+
+  #[test]
+  fn classifies_data_errors() {
+    let error = JsonDeserializeError::from_serde_json_error(
+      &serde_json::from_str::<u8>("\"not a number\"").unwrap_err(),
+      DataSetPath::new(),
+    );
+
+    assert_eq!(error.classify(), JsonErrorCategory::Data);
+    assert!(error.is_data());
+    assert!(!error.is_syntax());
+    assert!(!error.is_eof());
+  }
+
+  #[test]
+  fn classifies_unexpected_eof() {
+    let error =
+      JsonDeserializeError::from_serde_json_error(&parse_error("{\"a\":"), DataSetPath::new());
+
+    assert_eq!(error.classify(), JsonErrorCategory::Eof);
+    assert!(error.is_eof());
+  }
+
+  #[test]
+  fn unresolved_bulk_data_uri_classifies_as_data() {
+    let error = JsonDeserializeError::UnresolvedBulkDataUri {
+      uri: "http://example.com/bulk".to_string(),
+      details: "BulkDataURI could not be resolved".to_string(),
+      path: DataSetPath::new(),
+      location: None,
+    };
+
+    assert_eq!(error.classify(), JsonErrorCategory::Data);
+    assert!(error.is_data());
+  }
+
+  #[test]
+  fn from_serde_json_error_captures_line_and_column_but_not_byte_offset() {
+    let error =
+      JsonDeserializeError::from_serde_json_error(&parse_error("{ \"a\" }"), DataSetPath::new());
+
+    let location = error.path().clone();
+    assert!(location.is_empty());
 
-        lines
+    match error {
+      JsonDeserializeError::SyntaxError { location, .. } => {
+        let location = location.expect("syntax errors should carry a location");
+        assert_eq!(location.line, 1);
+        assert_eq!(location.column, 7);
+        assert_eq!(location.byte_offset, None);
       }
+      other => panic!("expected a SyntaxError, got {:?}", other),
     }
   }
+
+  #[test]
+  fn with_object_start_offset_fills_in_byte_offset() {
+    let error = JsonDeserializeError::from_serde_json_error(&parse_error("{"), DataSetPath::new())
+      .with_object_start_offset(42);
+
+    match error {
+      JsonDeserializeError::UnexpectedEof { location, .. } => {
+        assert_eq!(location.unwrap().byte_offset, Some(42));
+      }
+      other => panic!("expected an UnexpectedEof, got {:?}", other),
+    }
+  }
+
+  #[test]
+  fn to_lines_includes_tag_name_and_path_when_present() {
+    let mut path = DataSetPath::new();
+    path.add_data_element(DataElementTag {
+      group: 0x0010,
+      element: 0x0010,
+    });
+
+    let error = JsonDeserializeError::DataError {
+      details: "Invalid PersonName value".to_string(),
+      path,
+      location: None,
+    };
+
+    let lines = error.to_lines("while reading a data set");
+
+    assert!(lines
+      .iter()
+      .any(|line| line.contains("Invalid PersonName value")));
+    assert!(lines.iter().any(|line| line.starts_with("  Tag:")));
+    assert!(lines.iter().any(|line| line.starts_with("  Name:")));
+    assert!(lines.iter().any(|line| line.starts_with("  Path:")));
+  }
+
+  #[test]
+  fn to_lines_omits_tag_and_path_at_the_root() {
+    let error = JsonDeserializeError::SyntaxError {
+      details: "unexpected token".to_string(),
+      path: DataSetPath::new(),
+      location: None,
+    };
+
+    let lines = error.to_lines("while reading a data set");
+
+    assert!(!lines.iter().any(|line| line.starts_with("  Tag:")));
+    assert!(!lines.iter().any(|line| line.starts_with("  Path:")));
+  }
 }
@@ -0,0 +1,219 @@
+use dcmfx_core::{DataElementTag, DataSetPath};
+
+use crate::json_error::JsonDeserializeError;
+
+/// Tracks the current [`DataSetPath`] as a JSON deserializer descends into
+/// sequence items and their nested data sets, so that an error raised deep
+/// inside a nested structure reports the full `(group,element)[item]...`
+/// chain down to the failing element, rather than just the element itself.
+///
+/// This mirrors how `serde_path_to_error` wraps a `Deserializer` to track
+/// the path to a failure, but is specialized for the DICOM JSON Model's
+/// shape of data element -> sequence -> item -> data element.
+///
+#[derive(Debug, Default)]
+pub struct JsonPathTracker {
+  path: DataSetPath,
+}
+
+impl JsonPathTracker {
+  /// Creates a new, empty [`JsonPathTracker`] positioned at the root of the
+  /// data set.
+  ///
+  pub fn new() -> Self {
+    Self {
+      path: DataSetPath::new(),
+    }
+  }
+
+  /// Returns the current path.
+  ///
+  pub fn path(&self) -> &DataSetPath {
+    &self.path
+  }
+
+  /// Enters the data element with the given tag, e.g. when the decoder
+  /// starts reading the value for a `"GGGGEEEE"` key.
+  ///
+  pub fn enter_data_element(&mut self, tag: DataElementTag) {
+    self.path.add_data_element(tag);
+  }
+
+  /// Enters the item at `item_index` of the sequence the decoder is
+  /// currently positioned on.
+  ///
+  pub fn enter_sequence_item(&mut self, item_index: usize) {
+    self.path.add_sequence_item(item_index);
+  }
+
+  /// Leaves the most recently entered data element or sequence item,
+  /// restoring the path to what it was before the matching `enter_*` call.
+  ///
+  pub fn leave(&mut self) {
+    self.path.pop();
+  }
+}
+
+/// Walks a DICOM JSON data set object, recursing into nested sequence items
+/// and tracking the path to each data element via `tracker`, so that any
+/// error raised along the way carries the full path down to where it
+/// occurred.
+///
+/// This only validates the *shape* of the DICOM JSON Model -- that each key
+/// is a well-formed `"GGGGEEEE"` tag and that `SQ` values hold an array of
+/// nested data set objects -- decoding of individual element values is out
+/// of scope here and is left to the caller.
+///
+pub fn decode_data_set(
+  value: &serde_json::Value,
+  tracker: &mut JsonPathTracker,
+) -> Result<(), JsonDeserializeError> {
+  let object = value.as_object().ok_or_else(|| JsonDeserializeError::DataError {
+    details: "Expected a DICOM JSON data set to be a JSON object".to_string(),
+    path: tracker.path().clone(),
+    location: None,
+  })?;
+
+  for (key, element) in object {
+    let tag = parse_tag_key(key).ok_or_else(|| JsonDeserializeError::DataError {
+      details: format!("Invalid DICOM JSON tag key: \"{}\"", key),
+      path: tracker.path().clone(),
+      location: None,
+    })?;
+
+    tracker.enter_data_element(tag);
+    let result = decode_data_element(element, tracker);
+    tracker.leave();
+
+    result?;
+  }
+
+  Ok(())
+}
+
+/// Recurses into a single data element's value when it's a sequence (`SQ`),
+/// entering and leaving each item via `tracker` in turn.
+///
+fn decode_data_element(
+  element: &serde_json::Value,
+  tracker: &mut JsonPathTracker,
+) -> Result<(), JsonDeserializeError> {
+  let is_sequence = element.get("vr").and_then(|vr| vr.as_str()) == Some("SQ");
+
+  if !is_sequence {
+    return Ok(());
+  }
+
+  let items = element.get("Value").and_then(|value| value.as_array());
+
+  let Some(items) = items else {
+    return Ok(());
+  };
+
+  for (index, item) in items.iter().enumerate() {
+    tracker.enter_sequence_item(index);
+    let result = decode_data_set(item, tracker);
+    tracker.leave();
+
+    result?;
+  }
+
+  Ok(())
+}
+
+/// Parses a DICOM JSON `"GGGGEEEE"` tag key -- eight hex digits, group then
+/// element, with no separator -- into a [`DataElementTag`].
+///
+fn parse_tag_key(key: &str) -> Option<DataElementTag> {
+  if key.len() != 8 || !key.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+    return None;
+  }
+
+  Some(DataElementTag {
+    group: u16::from_str_radix(&key[0..4], 16).ok()?,
+    element: u16::from_str_radix(&key[4..8], 16).ok()?,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use dcmfx_core::dictionary;
+
+  use super::*;
+
+  /// Builds a three-level-deep nested sequence -- Referenced Series
+  /// Sequence -> Referenced Image Sequence -> Request Attributes Sequence
+  /// -- with a malformed tag key inside the innermost item, and checks that
+  /// the resulting error's path resolves, level by level, to the correct
+  /// tag and name at each nesting via `dictionary::tag_name`.
+  ///
+  #[test]
+  fn reports_path_through_nested_sequences() {
+    let referenced_series_sequence = DataElementTag {
+      group: 0x0008,
+      element: 0x1115,
+    };
+    let referenced_image_sequence = DataElementTag {
+      group: 0x0008,
+      element: 0x1140,
+    };
+    let request_attributes_sequence = DataElementTag {
+      group: 0x0040,
+      element: 0x0275,
+    };
+
+    let json = serde_json::json!({
+      "00081115": {
+        "vr": "SQ",
+        "Value": [
+          {
+            "00081140": {
+              "vr": "SQ",
+              "Value": [
+                {
+                  "00400275": {
+                    "vr": "SQ",
+                    "Value": [
+                      { "NOTATAG": { "vr": "UN" } }
+                    ]
+                  }
+                }
+              ]
+            }
+          }
+        ]
+      }
+    });
+
+    let mut tracker = JsonPathTracker::new();
+    let error = decode_data_set(&json, &mut tracker)
+      .expect_err("malformed innermost tag key should fail");
+
+    assert!(tracker.path().is_empty());
+
+    let path = error.path();
+
+    let tag = path
+      .final_data_element()
+      .expect("path should contain a data element");
+    assert_eq!(tag, request_attributes_sequence);
+    assert_eq!(
+      dictionary::tag_name(tag, None),
+      dictionary::tag_name(request_attributes_sequence, None)
+    );
+
+    let detailed = path.to_detailed_string();
+    assert!(detailed.contains(&dictionary::tag_name(
+      referenced_series_sequence,
+      None
+    )));
+    assert!(detailed.contains(&dictionary::tag_name(
+      referenced_image_sequence,
+      None
+    )));
+    assert!(detailed.contains(&dictionary::tag_name(
+      request_attributes_sequence,
+      None
+    )));
+  }
+}